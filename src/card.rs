@@ -1,6 +1,10 @@
-use enum_iterator::Sequence;
+use enum_iterator::{all, Sequence};
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence)]
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence, Serialize, Deserialize)]
 pub enum RegularCardValue {
     Two = 2,
     Three = 3,
@@ -21,9 +25,28 @@ impl RegularCardValue {
     pub fn numeric_value(&self) -> usize {
         *self as usize
     }
+
+    fn from_numeric_value(value: usize) -> RegularCardValue {
+        match value {
+            2 => RegularCardValue::Two,
+            3 => RegularCardValue::Three,
+            4 => RegularCardValue::Four,
+            5 => RegularCardValue::Five,
+            6 => RegularCardValue::Six,
+            7 => RegularCardValue::Seven,
+            8 => RegularCardValue::Eight,
+            9 => RegularCardValue::Nine,
+            10 => RegularCardValue::Ten,
+            11 => RegularCardValue::Jack,
+            12 => RegularCardValue::Queen,
+            13 => RegularCardValue::King,
+            14 => RegularCardValue::Ace,
+            _ => panic!("invalid numeric card value {}", value),
+        }
+    }
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence, Serialize, Deserialize)]
 pub enum RegularCardSuite {
     Heart,
     Diamond,
@@ -31,7 +54,28 @@ pub enum RegularCardSuite {
     Clubs,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence)]
+impl RegularCardSuite {
+    fn lane(&self) -> u32 {
+        match self {
+            RegularCardSuite::Heart => 0,
+            RegularCardSuite::Diamond => 1,
+            RegularCardSuite::Spade => 2,
+            RegularCardSuite::Clubs => 3,
+        }
+    }
+
+    fn from_lane(lane: u32) -> RegularCardSuite {
+        match lane {
+            0 => RegularCardSuite::Heart,
+            1 => RegularCardSuite::Diamond,
+            2 => RegularCardSuite::Spade,
+            3 => RegularCardSuite::Clubs,
+            _ => panic!("invalid suite lane {}", lane),
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence, Serialize, Deserialize)]
 pub enum SpecialCardType {
     Dragon,
     Phoenix,
@@ -39,7 +83,7 @@ pub enum SpecialCardType {
     Dog,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Sequence)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone, Sequence, Serialize, Deserialize)]
 pub enum Card {
     SpecialCard(SpecialCardType),
     RegularCard(RegularCardValue, RegularCardSuite),
@@ -95,13 +139,6 @@ impl Card {
         }
     }
 
-    pub fn value(&self) -> Option<RegularCardValue> {
-        match self {
-            Card::RegularCard(value, _) => Some(*value),
-            _ => None,
-        }
-    }
-
     pub fn numeric_value(&self) -> Option<usize> {
         match self {
             Card::SpecialCard(SpecialCardType::One) => Some(1),
@@ -127,4 +164,235 @@ impl Card {
             _ => 0,
         }
     }
+
+    /// The bit this card occupies in a [`CardSet`]: regular cards sit in one of four 13-bit
+    /// suit lanes, and the four special cards each get a dedicated bit above them.
+    fn bit_index(&self) -> u32 {
+        match self {
+            Card::RegularCard(value, suite) => {
+                suite.lane() * VALUES_PER_SUIT + (value.numeric_value() as u32 - 2)
+            }
+            Card::SpecialCard(SpecialCardType::Dragon) => DRAGON_BIT,
+            Card::SpecialCard(SpecialCardType::Phoenix) => PHOENIX_BIT,
+            Card::SpecialCard(SpecialCardType::One) => ONE_BIT,
+            Card::SpecialCard(SpecialCardType::Dog) => DOG_BIT,
+        }
+    }
+
+    fn from_bit_index(bit: u32) -> Card {
+        match bit {
+            DRAGON_BIT => Card::SpecialCard(SpecialCardType::Dragon),
+            PHOENIX_BIT => Card::SpecialCard(SpecialCardType::Phoenix),
+            ONE_BIT => Card::SpecialCard(SpecialCardType::One),
+            DOG_BIT => Card::SpecialCard(SpecialCardType::Dog),
+            _ => Card::RegularCard(
+                RegularCardValue::from_numeric_value((bit % VALUES_PER_SUIT) as usize + 2),
+                RegularCardSuite::from_lane(bit / VALUES_PER_SUIT),
+            ),
+        }
+    }
+}
+
+const VALUES_PER_SUIT: u32 = 13; // Two..=Ace
+const NUM_SUITS: u32 = 4;
+const NUM_REGULAR_BITS: u32 = VALUES_PER_SUIT * NUM_SUITS;
+const DRAGON_BIT: u32 = NUM_REGULAR_BITS;
+const PHOENIX_BIT: u32 = NUM_REGULAR_BITS + 1;
+const ONE_BIT: u32 = NUM_REGULAR_BITS + 2;
+const DOG_BIT: u32 = NUM_REGULAR_BITS + 3;
+const NUM_BITS: u32 = DOG_BIT + 1;
+
+/// A fixed-width bitmask with one bit per distinct card, used as an allocation-free
+/// representation of a set of cards by [`Hand`](crate::hand::Hand) and
+/// [`PlayerHand`](crate::game::PlayerHand).
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Default)]
+pub struct CardSet(u64);
+
+impl CardSet {
+    pub fn new() -> CardSet {
+        CardSet(0)
+    }
+
+    pub fn insert(&mut self, card: Card) {
+        self.0 |= 1 << card.bit_index();
+    }
+
+    pub fn remove(&mut self, card: Card) {
+        self.0 &= !(1 << card.bit_index());
+    }
+
+    pub fn contains(&self, card: Card) -> bool {
+        self.0 & (1 << card.bit_index()) != 0
+    }
+
+    pub fn contains_phoenix(&self) -> bool {
+        self.0 & (1 << PHOENIX_BIT) != 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Card> + '_ {
+        let bits = self.0;
+        (0..NUM_BITS)
+            .filter(move |bit| bits & (1 << bit) != 0)
+            .map(Card::from_bit_index)
+    }
+
+    fn value_mask(value: RegularCardValue) -> u64 {
+        (0..NUM_SUITS)
+            .map(|lane| 1u64 << (lane * VALUES_PER_SUIT + value.numeric_value() as u32 - 2))
+            .sum()
+    }
+
+    /// How many suits of `value` are present in the set (0..=4), across all four suit lanes.
+    pub fn count_of_value(&self, value: RegularCardValue) -> usize {
+        (self.0 & Self::value_mask(value)).count_ones() as usize
+    }
+
+    /// Whether all four suits of `value` are present, i.e. a quadruple-bomb candidate.
+    pub fn has_all_four_suits(&self, value: RegularCardValue) -> bool {
+        let mask = Self::value_mask(value);
+        self.0 & mask == mask
+    }
+
+    /// The number of cards present for every regular value, from Two to Ace.
+    pub fn value_histogram(&self) -> impl Iterator<Item = (RegularCardValue, usize)> + '_ {
+        all::<RegularCardValue>().map(|value| (value, self.count_of_value(value)))
+    }
+}
+
+impl FromIterator<Card> for CardSet {
+    fn from_iter<T: IntoIterator<Item = Card>>(iter: T) -> Self {
+        let mut set = CardSet::new();
+        for card in iter {
+            set.insert(card);
+        }
+        set
+    }
+}
+
+impl Serialize for CardSet {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut cards: Vec<Card> = self.iter().collect();
+        cards.sort_unstable();
+        cards.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CardSet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let cards = Vec::<Card>::deserialize(deserializer)?;
+        Ok(cards.into_iter().collect())
+    }
+}
+
+/// A card token (e.g. `"KH"`, `"Dr"`) did not match any known card.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CardParseError(String);
+
+impl fmt::Display for CardParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid card token {:?}", self.0)
+    }
+}
+
+impl std::error::Error for CardParseError {}
+
+impl FromStr for Card {
+    type Err = CardParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Dr" => return Ok(Card::SpecialCard(SpecialCardType::Dragon)),
+            "Ph" => return Ok(Card::SpecialCard(SpecialCardType::Phoenix)),
+            "Ma" => return Ok(Card::SpecialCard(SpecialCardType::One)),
+            "Do" => return Ok(Card::SpecialCard(SpecialCardType::Dog)),
+            _ => {}
+        }
+
+        let chars: Vec<char> = s.chars().collect();
+        let [value_char, suite_char] = chars.as_slice() else {
+            return Err(CardParseError(s.to_string()));
+        };
+
+        let value = parse_value_char(*value_char).ok_or_else(|| CardParseError(s.to_string()))?;
+        let suite = parse_suite_char(*suite_char).ok_or_else(|| CardParseError(s.to_string()))?;
+        Ok(Card::RegularCard(value, suite))
+    }
+}
+
+fn parse_value_char(c: char) -> Option<RegularCardValue> {
+    Some(match c {
+        '2' => RegularCardValue::Two,
+        '3' => RegularCardValue::Three,
+        '4' => RegularCardValue::Four,
+        '5' => RegularCardValue::Five,
+        '6' => RegularCardValue::Six,
+        '7' => RegularCardValue::Seven,
+        '8' => RegularCardValue::Eight,
+        '9' => RegularCardValue::Nine,
+        'T' => RegularCardValue::Ten,
+        'J' => RegularCardValue::Jack,
+        'Q' => RegularCardValue::Queen,
+        'K' => RegularCardValue::King,
+        'A' => RegularCardValue::Ace,
+        _ => return None,
+    })
+}
+
+fn parse_suite_char(c: char) -> Option<RegularCardSuite> {
+    Some(match c {
+        'H' => RegularCardSuite::Heart,
+        'D' => RegularCardSuite::Diamond,
+        'S' => RegularCardSuite::Spade,
+        'C' => RegularCardSuite::Clubs,
+        _ => return None,
+    })
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Card::RegularCard(value, suite) => write!(f, "{}{}", value_str(*value), suite_glyph(*suite)),
+            Card::SpecialCard(SpecialCardType::Dragon) => write!(f, "Dragon"),
+            Card::SpecialCard(SpecialCardType::Phoenix) => write!(f, "Phoenix"),
+            Card::SpecialCard(SpecialCardType::One) => write!(f, "Mahjong"),
+            Card::SpecialCard(SpecialCardType::Dog) => write!(f, "Dog"),
+        }
+    }
+}
+
+fn value_str(value: RegularCardValue) -> &'static str {
+    match value {
+        RegularCardValue::Two => "2",
+        RegularCardValue::Three => "3",
+        RegularCardValue::Four => "4",
+        RegularCardValue::Five => "5",
+        RegularCardValue::Six => "6",
+        RegularCardValue::Seven => "7",
+        RegularCardValue::Eight => "8",
+        RegularCardValue::Nine => "9",
+        RegularCardValue::Ten => "T",
+        RegularCardValue::Jack => "J",
+        RegularCardValue::Queen => "Q",
+        RegularCardValue::King => "K",
+        RegularCardValue::Ace => "A",
+    }
+}
+
+fn suite_glyph(suite: RegularCardSuite) -> char {
+    match suite {
+        RegularCardSuite::Heart => '♥',
+        RegularCardSuite::Diamond => '♦',
+        RegularCardSuite::Spade => '♠',
+        RegularCardSuite::Clubs => '♣',
+    }
 }
@@ -1,13 +1,21 @@
-use crate::card::Card;
+use crate::card::{Card, CardParseError, CardSet};
+use crate::hand::Hand;
 
 use enum_iterator::all;
 
+use itertools::Itertools;
 use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
-#[derive(PartialEq, Eq, Debug)]
-pub struct PlayerHand(Vec<Card>);
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct PlayerHand(CardSet);
+
+/// The deck stays a plain `Vec<Card>` rather than a [`CardSet`]: dealing is a sequential,
+/// order-dependent draw off the top, which an unordered bitset can't represent.
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Deck(Vec<Card>);
 
 pub const NUM_CARDS_PER_PLAYER: usize = 14;
@@ -21,9 +29,126 @@ impl Deck {
     }
 
     pub fn deal(&mut self) -> PlayerHand {
-        let mut cards = self.0.split_off(self.0.len() - NUM_CARDS_PER_PLAYER);
+        let cards = self.0.split_off(self.0.len() - NUM_CARDS_PER_PLAYER);
+        PlayerHand(cards.into_iter().collect())
+    }
+}
+
+impl PlayerHand {
+    /// Cards in this hand in ascending order, for the combinatorial enumeration below.
+    fn cards(&self) -> Vec<Card> {
+        let mut cards: Vec<Card> = self.0.iter().collect();
         cards.sort_unstable();
-        PlayerHand(cards)
+        cards
+    }
+
+    /// Enumerates every valid [`Hand`] that can be formed from this player hand, optionally
+    /// restricted to those that can be played on top of `table_hand`.
+    ///
+    /// This tries every subset of the hand, from a single card up to the whole hand, and keeps
+    /// the ones that form a valid hand type, the same combinatorial approach the validators in
+    /// [`crate::hand`] are built to be used with.
+    pub fn legal_plays(&self, table_hand: Option<&Hand>) -> Vec<Hand> {
+        let cards = self.cards();
+        (1..=cards.len())
+            .flat_map(|size| cards.iter().copied().combinations(size))
+            .filter_map(|cards| {
+                let hand = Hand::new(cards);
+                hand.hand_type()?;
+                Some(hand)
+            })
+            .filter(|hand| table_hand.is_none_or(|table_hand| hand > table_hand))
+            .collect()
     }
 }
 
+/// A player hand written as a whitespace-separated list of card tokens was malformed.
+#[derive(PartialEq, Eq, Debug)]
+pub enum PlayerHandParseError {
+    InvalidCard(CardParseError),
+    DuplicateCard(Card),
+    WrongSize(usize),
+}
+
+impl fmt::Display for PlayerHandParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayerHandParseError::InvalidCard(err) => write!(f, "{}", err),
+            PlayerHandParseError::DuplicateCard(card) => {
+                write!(f, "duplicate card {:?} in player hand", card)
+            }
+            PlayerHandParseError::WrongSize(actual) => write!(
+                f,
+                "player hand has {} cards, expected {}",
+                actual, NUM_CARDS_PER_PLAYER
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlayerHandParseError {}
+
+impl FromStr for PlayerHand {
+    type Err = PlayerHandParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cards = Vec::new();
+        for token in s.split_whitespace() {
+            let card = token
+                .parse::<Card>()
+                .map_err(PlayerHandParseError::InvalidCard)?;
+            if cards.contains(&card) {
+                return Err(PlayerHandParseError::DuplicateCard(card));
+            }
+            cards.push(card);
+        }
+        if cards.len() != NUM_CARDS_PER_PLAYER {
+            return Err(PlayerHandParseError::WrongSize(cards.len()));
+        }
+        Ok(PlayerHand(cards.into_iter().collect()))
+    }
+}
+
+impl fmt::Display for PlayerHand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cards = self.cards().iter().map(Card::to_string).collect::<Vec<_>>().join(" ");
+        write!(f, "{}", cards)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn deck_round_trips_through_serde_preserving_order() {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+        let deck = Deck::new(&mut rng);
+        let json = serde_json::to_string(&deck).unwrap();
+        let round_tripped: Deck = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deck, round_tripped);
+    }
+
+    #[test]
+    fn legal_plays_excludes_non_bombs_over_a_lone_dragon() {
+        let hand: PlayerHand = "AH KD QS JC TH 9D 8S 7C 6H 5D 4S 3C 2H Ma".parse().unwrap();
+        let dragon_single = Hand::single_card(Card::SpecialCard(crate::card::SpecialCardType::Dragon));
+
+        assert!(hand.legal_plays(Some(&dragon_single)).is_empty());
+    }
+
+    #[test]
+    fn legal_plays_without_a_table_hand_includes_every_single_card() {
+        let hand: PlayerHand = "AH KD QS JC TH 9D 8S 7C 6H 5D 4S 3C 2H Ma".parse().unwrap();
+
+        let singles = hand
+            .legal_plays(None)
+            .into_iter()
+            .filter(|played| played.hand_type() == Some(crate::hand::HandType::SingleCard))
+            .count();
+
+        assert_eq!(singles, NUM_CARDS_PER_PLAYER);
+    }
+}
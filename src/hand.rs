@@ -1,10 +1,22 @@
 use crate::card::*;
 use crate::util::iter_all_equal;
 
-use itertools::Itertools;
-use std::collections::HashMap;
+use enum_iterator::all;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+/// The card value a hand is ranked by, for hands of the same [`HandType`] and length. Special
+/// singles (Phoenix, Dragon, Mahjong, Dog) have no [`RegularCardValue`] of their own and are
+/// ranked separately by [`Card::can_be_played_on_top_of_single_card`] instead of this ordering.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum RelevantValue {
+    Value(RegularCardValue),
+    PhoenixSingle,
+    SpecialSingle(SpecialCardType),
+}
+
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
 pub enum HandType {
     SingleCard,
     Pair,
@@ -16,17 +28,32 @@ pub enum HandType {
     StraightBomb,
 }
 
-#[derive(PartialEq, Eq, Debug)]
-pub struct Hand(Vec<Card>);
+#[derive(PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Hand(CardSet);
 
 impl Hand {
-    pub fn new(mut cards: Vec<Card>) -> Hand {
-        cards.sort_unstable();
-        Hand(cards)
+    /// Builds a hand from a list of cards, which must be pairwise distinct: a `CardSet` can't
+    /// represent a duplicate physical card, so one would otherwise be silently dropped.
+    pub fn new(cards: Vec<Card>) -> Hand {
+        let num_cards = cards.len();
+        let set: CardSet = cards.into_iter().collect();
+        debug_assert_eq!(set.len(), num_cards, "Hand::new requires distinct cards");
+        Hand(set)
     }
 
     pub fn single_card(card: Card) -> Hand {
-        Hand(vec![card])
+        let mut cards = CardSet::new();
+        cards.insert(card);
+        Hand(cards)
+    }
+
+    /// Cards in this hand in ascending order. Most validators run straight off the bitset, but
+    /// straights need an ordered view to walk consecutive cards (and to accommodate the One,
+    /// which sits outside the regular value range the bitset's rank histogram covers).
+    fn cards(&self) -> Vec<Card> {
+        let mut cards: Vec<Card> = self.0.iter().collect();
+        cards.sort_unstable();
+        cards
     }
 
     pub fn hand_type(&self) -> Option<HandType> {
@@ -34,6 +61,10 @@ impl Hand {
             return None;
         }
 
+        if self.num_phoenices() == 1 {
+            return self.resolve_phoenix().map(|(hand_type, _)| hand_type);
+        }
+
         match self {
             hand if hand.is_valid_quadruple_bomb() => Some(HandType::QuadrupleBomb),
             hand if hand.is_valid_straight_bomb() => Some(HandType::StraightBomb),
@@ -48,12 +79,7 @@ impl Hand {
     }
 
     pub fn is_valid_quadruple_bomb(&self) -> bool {
-        match self.0.as_slice() {
-            [Card::RegularCard(_, _), Card::RegularCard(_, _), Card::RegularCard(_, _), Card::RegularCard(_, _)] => {
-                iter_all_equal(self.0.iter().map(|card| card.value())).is_some()
-            }
-            _ => false,
-        }
+        self.0.len() == 4 && all::<RegularCardValue>().any(|value| self.0.has_all_four_suits(value))
     }
 
     pub fn is_valid_straight_bomb(&self) -> bool {
@@ -67,173 +93,325 @@ impl Hand {
     }
 
     pub fn is_valid_pair(&self) -> bool {
-        match self.0.as_slice() {
-            [Card::RegularCard(_, _), Card::RegularCard(_, _)] => {
-                iter_all_equal(self.0.iter().map(|card| card.value())).is_some()
-            }
-            [Card::SpecialCard(SpecialCardType::Phoenix), Card::RegularCard(_, _)] => true,
-            _ => false,
-        }
+        self.0.len() == 2 && all::<RegularCardValue>().any(|value| self.0.count_of_value(value) == 2)
     }
 
     pub fn is_valid_triple(&self) -> bool {
-        match self.0.as_slice() {
-            [Card::RegularCard(value1, _), Card::RegularCard(value2, _), Card::RegularCard(value3, _)]
-                if value1 == value2 && value2 == value3 =>
-            {
-                true
-            }
-            [Card::SpecialCard(SpecialCardType::Phoenix), Card::RegularCard(value1, _), Card::RegularCard(value2, _)]
-                if value1 == value2 =>
-            {
-                true
-            }
-            _ => false,
-        }
+        self.0.len() == 3 && all::<RegularCardValue>().any(|value| self.0.count_of_value(value) == 3)
     }
 
     pub fn is_valid_straight(&self) -> bool {
-        if self.0.as_slice().windows(2).any(|pair| {
+        if self.0.len() < 5 {
+            return false;
+        }
+        self.cards().windows(2).all(|pair| {
             pair[0]
                 .numeric_value()
                 .zip(pair[1].numeric_value())
-                .map_or(false, |(left, right)| left == right)
-        }) {
-            return false;
-        }
-        let num_phoenices = self.num_phoenices();
-        let num_phoenices_needed = self
-            .0
-            .as_slice()
-            .windows(2)
-            .filter_map(|cards| match cards {
-                [card1, card2] => match (card1.numeric_value(), card2.numeric_value()) {
-                    (Some(value1), Some(value2)) => Some(value2 - value1 - 1),
-                    _ => None,
-                },
-                _ => panic!(),
-            })
-            .sum();
-        self.0.len() >= 5 && num_phoenices >= num_phoenices_needed
+                .is_some_and(|(left, right)| right == left + 1)
+        })
     }
 
     pub fn is_valid_straight_of_pairs(&self) -> bool {
-        let num_phoenices = self.num_phoenices();
-        let mut num_phoenices_needed = 0;
-        let first_value = self.0.iter().filter_map(|card| card.numeric_value()).next();
-        let last_value = self
-            .0
+        let histogram: Vec<(RegularCardValue, usize)> = self.0.value_histogram().collect();
+        let present: Vec<(RegularCardValue, usize)> = histogram
             .iter()
-            .map(|card| card.numeric_value().unwrap())
-            .next_back();
+            .copied()
+            .filter(|&(_, count)| count > 0)
+            .collect();
 
-        let Some(first_value) = first_value else {
+        let (Some(&(first_value, _)), Some(&(last_value, _))) = (present.first(), present.last())
+        else {
             return false;
         };
-        let Some(last_value) = last_value else {
+
+        // Every card must belong to the run: this also rejects a lone Mahjong tagging along,
+        // since it has no regular value and so isn't part of the histogram at all.
+        let run_length = last_value.numeric_value() - first_value.numeric_value() + 1;
+        if self.0.len() != run_length * 2 {
             return false;
-        };
+        }
 
-        const NUM_CARDS: usize = 2;
+        histogram.iter().all(|&(value, count)| {
+            if value < first_value || value > last_value {
+                count == 0
+            } else {
+                count == 2
+            }
+        })
+    }
 
-        let num_cards_by_value = self
+    pub fn is_valid_full_house(&self) -> bool {
+        if self.0.len() != 5 {
+            return false;
+        }
+        let counts: Vec<usize> = self
             .0
-            .iter()
-            .chunk_by(|card| card.numeric_value())
-            .into_iter()
-            .filter(|(value, _)| value.is_some())
-            .filter_map(|(value, cards)| match cards.count() {
-                length if length > NUM_CARDS => None,
-                length => Some((value, length)),
-            })
-            .collect::<HashMap<_, _>>();
-
-        for value in first_value..=last_value {
-            let num_cards_for_current_value = num_cards_by_value
-                .get(&Some(value))
-                .unwrap_or(&(0 as usize));
-            if *num_cards_for_current_value > NUM_CARDS {
-                return false;
-            }
-            num_phoenices_needed += NUM_CARDS - num_cards_for_current_value;
+            .value_histogram()
+            .map(|(_, count)| count)
+            .filter(|&count| count > 0)
+            .collect();
+        counts.iter().sum::<usize>() == 5
+            && counts.len() == 2
+            && counts.contains(&3)
+            && counts.contains(&2)
+    }
+
+    /// Classifies a Phoenix-containing hand by substituting the Phoenix for every candidate
+    /// regular card in turn and keeping the substitution that forms the strongest valid
+    /// (non-bomb) hand, mirroring how a joker is assigned the rank that benefits the hand most.
+    fn resolve_phoenix(&self) -> Option<(HandType, RelevantValue)> {
+        if self.0.len() == 1 {
+            return Some((HandType::SingleCard, RelevantValue::PhoenixSingle));
+        }
+
+        if !self.0.contains_phoenix() {
+            return None;
         }
 
-        num_phoenices >= num_phoenices_needed
+        let mut without_phoenix = self.0;
+        without_phoenix.remove(Card::SpecialCard(SpecialCardType::Phoenix));
+
+        all::<RegularCardValue>()
+            .filter_map(|value| {
+                let free_suite = all::<RegularCardSuite>()
+                    .find(|&suite| !without_phoenix.contains(Card::RegularCard(value, suite)))?;
+
+                let mut substituted = without_phoenix;
+                substituted.insert(Card::RegularCard(value, free_suite));
+                let hand = Hand(substituted);
+
+                let hand_type = match &hand {
+                    hand if hand.is_valid_pair() => HandType::Pair,
+                    hand if hand.is_valid_triple() => HandType::Triple,
+                    hand if hand.is_valid_straight() => HandType::Straight,
+                    hand if hand.is_valid_straight_of_pairs() => HandType::StraightOfPairs,
+                    hand if hand.is_valid_full_house() => HandType::FullHouse,
+                    _ => return None,
+                };
+
+                hand.relevant_card_value()
+                    .map(|relevant_value| (hand_type, relevant_value))
+            })
+            .max_by_key(|(_, value)| *value)
     }
 
-    pub fn is_valid_full_house(&self) -> bool {
-        match self.0.as_slice() {
-            [Card::RegularCard(value1, _), Card::RegularCard(value2, _), Card::RegularCard(value3, _), Card::RegularCard(value4, _), Card::RegularCard(value5, _)] => {
-                value1 == value2 && value4 == value5 && (value3 == value2 || value3 == value4)
+    pub fn num_phoenices(&self) -> usize {
+        if self.0.contains_phoenix() {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn relevant_card_value(&self) -> Option<RelevantValue> {
+        if self.num_phoenices() == 1 {
+            return self.resolve_phoenix().map(|(_, value)| value);
+        }
+
+        if self.is_valid_single_card() {
+            if let Card::SpecialCard(special) = self.cards()[0] {
+                return Some(RelevantValue::SpecialSingle(special));
             }
-            [Card::SpecialCard(SpecialCardType::Phoenix), Card::RegularCard(value1, _), Card::RegularCard(value2, _), Card::RegularCard(value3, _), Card::RegularCard(value4, _)] => {
-                ((value1 == value2 && value2 == value3)
-                    || (value1 == value2 && value3 == value4)
-                    || (value2 == value3 && value3 == value4))
-                    && value1 != value4
+        }
+
+        match self.hand_type()? {
+            HandType::FullHouse => {
+                let (triple_value, _) = self.0.value_histogram().find(|&(_, count)| count == 3)?;
+                Some(RelevantValue::Value(triple_value))
+            }
+            _ => {
+                let (highest_value, _) = self.0.value_histogram().filter(|&(_, count)| count > 0).last()?;
+                Some(RelevantValue::Value(highest_value))
             }
-            _ => false,
         }
     }
 
-    pub fn num_phoenices(&self) -> usize {
-        self.0
-            .iter()
-            .filter(|card| match card {
-                Card::SpecialCard(SpecialCardType::Phoenix) => true,
-                _ => false,
-            })
-            .count()
-    }
-
-    pub fn relevant_card_value(&self) -> Option<RegularCardValue> {
-        self.hand_type()
-            .map(|hand_type| match hand_type {
-                HandType::SingleCard
-                | HandType::Pair
-                | HandType::Triple
-                | HandType::Straight
-                | HandType::StraightOfPairs
-                | HandType::QuadrupleBomb
-                | HandType::StraightBomb => self.0.iter().next_back().unwrap(),
-                HandType::FullHouse => match self.0[0] {
-                    Card::RegularCard(_, _) => &self.0[2],
-                    Card::SpecialCard(SpecialCardType::Phoenix) => &self.0[3],
-                    _ => panic!(),
-                },
-            })
-            .map(|card| match card {
-                Card::RegularCard(value, _) => *value,
-                _ => panic!(),
-            })
+    pub fn is_bomb(&self) -> bool {
+        matches!(self.hand_type(), Some(HandType::StraightBomb) | Some(HandType::QuadrupleBomb))
     }
 
-    pub fn is_bomb(&self) -> bool {
-        match self.hand_type() {
-            Some(HandType::StraightBomb) | Some(HandType::QuadrupleBomb) => true,
-            _ => false,
+    /// Ranks two single-card hands via [`Card::can_be_played_on_top_of_single_card`] rather than
+    /// [`RelevantValue`], since a lone Phoenix or Dragon doesn't fit on a single linear scale.
+    fn single_card_cmp(self_card: Card, other_card: Card) -> std::cmp::Ordering {
+        if self_card.can_be_played_on_top_of_single_card(&other_card) {
+            std::cmp::Ordering::Greater
+        } else if other_card.can_be_played_on_top_of_single_card(&self_card) {
+            std::cmp::Ordering::Less
+        } else {
+            std::cmp::Ordering::Equal
         }
     }
+}
 
-    pub fn higher_value_than(&self, other: &Hand) -> bool {
-        self.relevant_card_value()
-            .zip(other.relevant_card_value())
-            .map(|(self_value, other_value)| self_value > other_value)
-            .unwrap_or(false)
+impl PartialOrd for Hand {
+    /// Orders hands by playability: bombs outrank non-bombs, and two non-bomb hands only compare
+    /// when they share a `HandType` and length (`None` otherwise). Singles use
+    /// [`Hand::single_card_cmp`]; other hand types compare by [`Hand::relevant_card_value`].
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self.is_bomb(), other.is_bomb()) {
+            (true, true) => Some(
+                self.0
+                    .len()
+                    .cmp(&other.0.len())
+                    .then_with(|| self.relevant_card_value().cmp(&other.relevant_card_value())),
+            ),
+            (true, false) => Some(std::cmp::Ordering::Greater),
+            (false, true) => Some(std::cmp::Ordering::Less),
+            (false, false) => {
+                let hand_type = self.hand_type();
+                if hand_type != other.hand_type() || self.0.len() != other.0.len() {
+                    None
+                } else if hand_type == Some(HandType::SingleCard) {
+                    Some(Self::single_card_cmp(self.cards()[0], other.cards()[0]))
+                } else {
+                    Some(self.relevant_card_value().cmp(&other.relevant_card_value()))
+                }
+            }
+        }
     }
+}
 
-    pub fn can_be_played_on(&self, other: &Hand) -> bool {
-        if self.is_bomb() {
-            if other.is_bomb() {
-                self.0.len() > other.0.len()
-                    || (self.0.len() == other.0.len() && self.higher_value_than(other))
-            } else {
-                true
+/// A hand written as a whitespace-separated list of card tokens (e.g. `"KH QH JH TH 9H"`)
+/// was malformed.
+#[derive(PartialEq, Eq, Debug)]
+pub enum HandParseError {
+    InvalidCard(CardParseError),
+    DuplicateCard(Card),
+}
+
+impl fmt::Display for HandParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandParseError::InvalidCard(err) => write!(f, "{}", err),
+            HandParseError::DuplicateCard(card) => write!(f, "duplicate card {:?} in hand", card),
+        }
+    }
+}
+
+impl std::error::Error for HandParseError {}
+
+impl FromStr for Hand {
+    type Err = HandParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cards = Vec::new();
+        for token in s.split_whitespace() {
+            let card = token.parse::<Card>().map_err(HandParseError::InvalidCard)?;
+            if cards.contains(&card) {
+                return Err(HandParseError::DuplicateCard(card));
             }
-        } else {
-            self.hand_type() == other.hand_type()
-                && self.0.len() == other.0.len()
-                && self.higher_value_than(other)
+            cards.push(card);
         }
+        Ok(Hand::new(cards))
+    }
+}
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cards = self.cards().iter().map(Card::to_string).collect::<Vec<_>>().join(" ");
+        write!(
+            f,
+            "{} ({:?}, relevant value {:?})",
+            cards,
+            self.hand_type(),
+            self.relevant_card_value()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserializing_a_hand_reorders_like_new() {
+        let straight: Hand = "2H 3H 4H 5H 6H".parse().unwrap();
+        let shuffled: Vec<Card> = "6H 2H 4H 3H 5H"
+            .split_whitespace()
+            .map(|token| token.parse().unwrap())
+            .collect();
+        let via_json: Hand = serde_json::from_str(&serde_json::to_string(&shuffled).unwrap()).unwrap();
+
+        assert_eq!(straight, via_json);
+        assert_eq!(via_json.hand_type(), Some(HandType::StraightBomb));
+    }
+
+    #[test]
+    fn phoenix_fills_a_straight_gap() {
+        let hand: Hand = "2H 3H 4H 6H Ph".parse().unwrap();
+
+        assert_eq!(hand.hand_type(), Some(HandType::Straight));
+        assert_eq!(hand.relevant_card_value(), Some(RelevantValue::Value(RegularCardValue::Six)));
+    }
+
+    #[test]
+    fn phoenix_extends_a_pair_to_a_triple() {
+        let hand: Hand = "5H 5D Ph".parse().unwrap();
+
+        assert_eq!(hand.hand_type(), Some(HandType::Triple));
+    }
+
+    #[test]
+    fn phoenix_completes_a_full_house() {
+        let hand: Hand = "5H 5D 5S 9H Ph".parse().unwrap();
+
+        assert_eq!(hand.hand_type(), Some(HandType::FullHouse));
+        assert_eq!(hand.relevant_card_value(), Some(RelevantValue::Value(RegularCardValue::Five)));
+    }
+
+    #[test]
+    fn phoenix_completes_a_straight_of_pairs() {
+        let hand: Hand = "2H 2D 3H 3D 4H Ph".parse().unwrap();
+
+        assert_eq!(hand.hand_type(), Some(HandType::StraightOfPairs));
+    }
+
+    #[test]
+    fn phoenix_cannot_complete_a_bomb() {
+        let hand: Hand = "5H 5D 5S 5C Ph".parse().unwrap();
+
+        assert!(!hand.is_bomb());
+    }
+
+    #[test]
+    fn ace_single_cannot_beat_dragon_single() {
+        let ace: Hand = "AH".parse().unwrap();
+        let dragon: Hand = "Dr".parse().unwrap();
+
+        assert!(ace < dragon);
+    }
+
+    #[test]
+    fn phoenix_single_loses_to_dragon_single() {
+        let phoenix: Hand = "Ph".parse().unwrap();
+        let dragon: Hand = "Dr".parse().unwrap();
+
+        assert!(phoenix < dragon);
+        assert!(dragon > phoenix);
+    }
+
+    #[test]
+    fn mahjong_single_beats_dog_single() {
+        let mahjong: Hand = "Ma".parse().unwrap();
+        let dog: Hand = "Do".parse().unwrap();
+
+        assert!(mahjong > dog);
+    }
+
+    #[test]
+    fn bomb_beats_any_non_bomb() {
+        let bomb: Hand = "2H 2D 2S 2C".parse().unwrap();
+        let straight: Hand = "3H 4D 5H 6D 7H".parse().unwrap();
+
+        assert!(bomb > straight);
+    }
+
+    #[test]
+    fn non_bomb_hands_of_different_type_are_incomparable() {
+        let pair: Hand = "2H 2D".parse().unwrap();
+        let triple: Hand = "3H 3D 3S".parse().unwrap();
+
+        assert_eq!(pair.partial_cmp(&triple), None);
     }
 }
@@ -1,51 +1,23 @@
 mod card;
+mod game;
 mod hand;
 mod util;
 
-use enum_iterator::all;
-
-use rand::{seq::SliceRandom, SeedableRng};
+use rand::SeedableRng;
 
 use card::*;
+use game::*;
 use hand::*;
 
-#[derive(PartialEq, Eq, Debug)]
-struct PlayerHand(Vec<Card>);
-
-#[derive(PartialEq, Eq, Debug)]
-struct Deck(Vec<Card>);
-
-const NUM_CARDS_PER_PLAYER: usize = 14;
-const NUM_PLAYERS: usize = 4;
-
-impl Deck {
-    fn new<R: rand::RngCore>(rng: &mut R) -> Deck {
-        let mut cards = all::<Card>().collect::<Vec<_>>();
-        cards.as_mut_slice().shuffle(rng);
-        Deck(cards)
-    }
-
-    fn deal(&mut self) -> PlayerHand {
-        let mut cards = self.0.split_off(self.0.len() - NUM_CARDS_PER_PLAYER);
-        cards.sort_unstable();
-        PlayerHand(cards)
-    }
-}
-
 fn print_hand(hand: &Hand) {
-    println!(
-        "hand {:?} has type {:?} at relevant value {:?}",
-        hand,
-        hand.hand_type(),
-        hand.relevant_card_value()
-    );
+    println!("hand {}", hand);
 }
 
 fn main() {
     let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(10);
     let mut deck = Deck::new(&mut rng);
     for i in 0..NUM_PLAYERS {
-        println!("Player {} is dealt hand {:?}", i, deck.deal());
+        println!("Player {} is dealt hand {}", i, deck.deal());
     }
     let card1 = Card::RegularCard(RegularCardValue::King, RegularCardSuite::Heart);
     let card2 = Card::RegularCard(RegularCardValue::Four, RegularCardSuite::Clubs);
@@ -112,7 +84,7 @@ fn main() {
     let hand6 = Hand::new(vec![
         Card::RegularCard(RegularCardValue::Two, RegularCardSuite::Heart),
         Card::RegularCard(RegularCardValue::Two, RegularCardSuite::Clubs),
-        Card::RegularCard(RegularCardValue::Two, RegularCardSuite::Clubs),
+        Card::RegularCard(RegularCardValue::Two, RegularCardSuite::Diamond),
         Card::RegularCard(RegularCardValue::Four, RegularCardSuite::Diamond),
         Card::RegularCard(RegularCardValue::Four, RegularCardSuite::Spade),
     ]);
@@ -135,4 +107,21 @@ fn main() {
         Card::RegularCard(RegularCardValue::Two, RegularCardSuite::Spade),
     ]);
     print_hand(&hand8);
+
+    let dragon_single = Hand::single_card(Card::SpecialCard(SpecialCardType::Dragon));
+    let ace_single = Hand::single_card(Card::RegularCard(RegularCardValue::Ace, RegularCardSuite::Heart));
+    let phoenix_single = Hand::single_card(Card::SpecialCard(SpecialCardType::Phoenix));
+    let mahjong_single = Hand::single_card(Card::SpecialCard(SpecialCardType::One));
+    let dog_single = Hand::single_card(Card::SpecialCard(SpecialCardType::Dog));
+    println!("ace single > dragon single: {:?}", ace_single > dragon_single);
+    println!("phoenix single > dragon single: {:?}", phoenix_single > dragon_single);
+    println!("dragon single > phoenix single: {:?}", dragon_single > phoenix_single);
+    println!("mahjong single > dog single: {:?}", mahjong_single > dog_single);
+
+    let player_hand: PlayerHand = "AH KD QS JC TH 9D 8S 7C 6H 5D 4S 3C 2H Ma".parse().unwrap();
+    let legal_over_dragon = player_hand.legal_plays(Some(&dragon_single));
+    println!(
+        "legal plays over a lone Dragon from {}: {:?}",
+        player_hand, legal_over_dragon
+    );
 }